@@ -1,98 +1,214 @@
-//! Comparison of unsigned Variable Integer encodings.
+//! Throughput comparison of unsigned Variable Integer encodings.
 //!
 //! This compares the [mutiformat's unsigned-varint
 //! encoding](https://github.com/multiformats/unsigned-varint) from the [unsigned-varint
 //! crate](https://crates.io/crates/unsigned-varint) with [QUIC's Variable-Length Integer
 //! Encoding](https://www.rfc-editor.org/rfc/rfc9000.html#name-variable-length-integer-enc).
 //!
-//! This benchmark limits itself to encoding and decoding u64.
+//! This benchmark limits itself to encoding and decoding u64. Each iteration runs the whole
+//! codec over a pre-generated dataset into a single reused buffer, rather than timing one value
+//! per iteration, so results reflect codec throughput rather than `criterion` call overhead.
 
 use varint_bench::VarInt;
 
-use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use bytes::{Buf, BytesMut};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use rand::seq::SliceRandom;
+use rand::Rng;
 
-fn rand_u62() -> u64 {
-    loop {
-        let n: u64 = rand::random();
-        if n <= 2u64.pow(62) {
-            return n;
-        }
-    }
+/// Number of values in each generated dataset.
+const DATASET_SIZE: usize = 10_000;
+
+/// Inclusive value ranges for the four QUIC varint size classes: 1, 2, 4, and 8 bytes.
+fn size_classes() -> [(u64, u64); 4] {
+    [
+        (0, 2u64.pow(6) - 1),
+        (2u64.pow(6), 2u64.pow(14) - 1),
+        (2u64.pow(14), 2u64.pow(30) - 1),
+        (2u64.pow(30), 2u64.pow(62) - 1),
+    ]
 }
 
-pub fn encode(c: &mut Criterion) {
-    let mut group = c.benchmark_group("Encoding");
+/// Builds a dataset of `len` values drawn from `classes` (cycled round-robin), then shuffles
+/// them so no single size class dominates a contiguous run.
+fn dataset_from_classes(len: usize, classes: &[(u64, u64)]) -> Vec<u64> {
+    let mut rng = rand::thread_rng();
+    let mut values: Vec<u64> = (0..len)
+        .map(|i| {
+            let (lo, hi) = classes[i % classes.len()];
+            rng.gen_range(lo..=hi)
+        })
+        .collect();
+    values.shuffle(&mut rng);
+    values
+}
+
+/// An even mix of all four size classes.
+fn mixed_dataset(len: usize) -> Vec<u64> {
+    dataset_from_classes(len, &size_classes())
+}
+
+/// Mostly 1- and 2-byte values, as seen in workloads dominated by small counters and lengths.
+fn small_dataset(len: usize) -> Vec<u64> {
+    let classes = size_classes();
+    dataset_from_classes(len, &[classes[0], classes[0], classes[0], classes[1]])
+}
+
+/// Mostly 4- and 8-byte values, as seen in workloads dominated by large offsets and ids.
+fn large_dataset(len: usize) -> Vec<u64> {
+    let classes = size_classes();
+    dataset_from_classes(len, &[classes[2], classes[3], classes[3], classes[3]])
+}
+
+/// Total bytes QUIC's encoding spends on `dataset`.
+fn encoded_len(dataset: &[u64]) -> usize {
+    dataset
+        .iter()
+        .map(|&n| VarInt::try_from(n).unwrap().size())
+        .sum()
+}
+
+/// Total bytes multiformat's LEB128 encoding spends on `dataset`, which differs from
+/// [`encoded_len`] since the two schemes don't share size classes (e.g. `n=64` is 1 byte in
+/// LEB128 but 2 bytes as a QUIC varint).
+fn multiformat_encoded_len(dataset: &[u64]) -> usize {
+    dataset
+        .iter()
+        .map(|&n| {
+            let mut tmp = [0u8; 10];
+            unsigned_varint::encode::u64(n, &mut tmp).len()
+        })
+        .sum()
+}
+
+fn encode_group(c: &mut Criterion, name: &str, dataset: &[u64]) {
+    let total_bytes = encoded_len(dataset);
+    let multiformat_bytes = multiformat_encoded_len(dataset);
+    let mut group = c.benchmark_group(format!("Encoding/{name}"));
+
+    group.throughput(Throughput::Bytes(multiformat_bytes as u64));
     group.bench_function("multiformat", |bencher| {
-        bencher.iter_batched(
-            // setup
-            || {
-                let n = rand_u62();
-                (n, [0u8; 10])
-            },
-            // routine
-            |(n, mut buf)| {
-                // returns a slice of buf: nothing to drop
-                unsigned_varint::encode::u64(black_box(n), &mut buf);
-            },
-            BatchSize::SmallInput,
-        )
+        let mut buf = Vec::with_capacity(multiformat_bytes);
+        bencher.iter(|| {
+            buf.clear();
+            for &n in dataset {
+                let mut tmp = [0u8; 10];
+                let slice = unsigned_varint::encode::u64(black_box(n), &mut tmp);
+                buf.extend_from_slice(slice);
+            }
+        })
     });
+
+    group.throughput(Throughput::Bytes(total_bytes as u64));
     group.bench_function("quic", |bencher| {
-        bencher.iter_batched(
-            // setup
-            || {
-                let n = rand_u62();
-                (VarInt::try_from(n).unwrap(), [0u8; 8])
-            },
-            // routine
-            |(n, mut buf)| {
-                // returns unit: nothing to drop
-                n.encode(&mut buf[..]).unwrap();
-            },
-            BatchSize::SmallInput,
-        )
+        let mut buf = Vec::with_capacity(total_bytes);
+        bencher.iter(|| {
+            buf.clear();
+            for &n in dataset {
+                VarInt::try_from(black_box(n)).unwrap().encode(&mut buf).unwrap();
+            }
+        })
+    });
+    group.bench_function("quic-buf", |bencher| {
+        let mut buf = BytesMut::with_capacity(total_bytes);
+        bencher.iter(|| {
+            buf.clear();
+            for &n in dataset {
+                VarInt::try_from(black_box(n)).unwrap().encode_buf(&mut buf);
+            }
+        })
     });
+    group.bench_function("quic-slice", |bencher| {
+        let mut buf = vec![0u8; total_bytes];
+        bencher.iter(|| {
+            let mut offset = 0;
+            for &n in dataset {
+                let written = VarInt::try_from(black_box(n))
+                    .unwrap()
+                    .encode_to_slice(&mut buf[offset..])
+                    .len();
+                offset += written;
+            }
+        })
+    });
+
+    group.finish();
 }
 
-pub fn decode(c: &mut Criterion) {
-    let mut group = c.benchmark_group("Decoding");
+fn decode_group(c: &mut Criterion, name: &str, dataset: &[u64]) {
+    let total_bytes = encoded_len(dataset);
+    let multiformat_bytes = multiformat_encoded_len(dataset);
+    let mut group = c.benchmark_group(format!("Decoding/{name}"));
+
+    group.throughput(Throughput::Bytes(multiformat_bytes as u64));
     group.bench_function("multiformat", |bencher| {
-        bencher.iter_batched(
-            // setup
-            || {
-                let n = rand_u62();
-                let mut buf = [0u8; 10];
-                let slice = unsigned_varint::encode::u64(n, &mut buf);
-                let mut buf = Vec::with_capacity(slice.len());
-                buf.extend_from_slice(slice);
-                buf
-            },
-            // routine
-            |buf| {
-                // returns u64 on stack: nothing to drop
-                unsigned_varint::io::read_u64(buf.as_slice()).unwrap();
-            },
-            BatchSize::SmallInput,
-        )
+        let mut encoded = Vec::with_capacity(multiformat_bytes);
+        for &n in dataset {
+            let mut tmp = [0u8; 10];
+            encoded.extend_from_slice(unsigned_varint::encode::u64(n, &mut tmp));
+        }
+        bencher.iter(|| {
+            let mut rest = encoded.as_slice();
+            while !rest.is_empty() {
+                let (_, tail) = unsigned_varint::decode::u64(black_box(rest)).unwrap();
+                rest = tail;
+            }
+        })
     });
+
+    group.throughput(Throughput::Bytes(total_bytes as u64));
     group.bench_function("quic", |bencher| {
-        bencher.iter_batched(
-            // setup
-            || {
-                let mut buf = Vec::with_capacity(8);
-                let n = rand_u62();
-                let n = VarInt::try_from(n).unwrap();
-                n.encode(&mut buf).unwrap();
-                buf
-            },
-            // routine
-            |buf| {
-                // returns VarInt(u64), same layout as u64: nothing to drop
-                VarInt::decode(&buf[..]).unwrap();
-            },
-            BatchSize::SmallInput,
-        )
+        let mut encoded = Vec::with_capacity(total_bytes);
+        for &n in dataset {
+            VarInt::try_from(n).unwrap().encode(&mut encoded).unwrap();
+        }
+        bencher.iter(|| {
+            let mut cursor = encoded.as_slice();
+            while !cursor.is_empty() {
+                VarInt::decode(black_box(&mut cursor)).unwrap();
+            }
+        })
+    });
+    group.bench_function("quic-buf", |bencher| {
+        let mut buf = BytesMut::with_capacity(total_bytes);
+        for &n in dataset {
+            VarInt::try_from(n).unwrap().encode_buf(&mut buf);
+        }
+        let encoded = buf.freeze();
+        bencher.iter(|| {
+            let mut rest = encoded.clone();
+            while rest.has_remaining() {
+                VarInt::decode_buf(black_box(&mut rest)).unwrap();
+            }
+        })
     });
+    group.bench_function("quic-slice", |bencher| {
+        let mut encoded = Vec::with_capacity(total_bytes);
+        for &n in dataset {
+            VarInt::try_from(n).unwrap().encode(&mut encoded).unwrap();
+        }
+        bencher.iter(|| {
+            let mut rest = encoded.as_slice();
+            while !rest.is_empty() {
+                let (_, tail) = VarInt::decode_from_slice(black_box(rest)).unwrap();
+                rest = tail;
+            }
+        })
+    });
+
+    group.finish();
+}
+
+pub fn encode(c: &mut Criterion) {
+    encode_group(c, "mixed", &mixed_dataset(DATASET_SIZE));
+    encode_group(c, "small", &small_dataset(DATASET_SIZE));
+    encode_group(c, "large", &large_dataset(DATASET_SIZE));
+}
+
+pub fn decode(c: &mut Criterion) {
+    decode_group(c, "mixed", &mixed_dataset(DATASET_SIZE));
+    decode_group(c, "small", &small_dataset(DATASET_SIZE));
+    decode_group(c, "large", &large_dataset(DATASET_SIZE));
 }
 
 criterion_group!(benches, encode, decode);
@@ -1,14 +1,25 @@
 //! QUIC Variable-Length Integer Encoding.
 //!
-//! This is an unsigned integer type encoded 1, 2, 4, or 8 bytes and can store values up to
-//! 2**62.
+//! [`VarInt`] is an unsigned integer type encoded 1, 2, 4, or 8 bytes and can store values up to
+//! 2**62. [`SignedVarInt`] wraps it with a zig-zag mapping to support negative values at the
+//! same compactness.
 //!
 //! See https://www.rfc-editor.org/rfc/rfc9000.html#name-variable-length-integer-enc.
+//!
+//! Besides the `std::io::{Read, Write}`-based [`VarInt::encode`]/[`VarInt::decode`], this crate
+//! provides a few other codec surfaces for the same wire format:
+//! - [`VarInt::encode_buf`]/[`VarInt::decode_buf`], zero-copy over `bytes::{Buf, BufMut}`
+//! - [`VarInt::encode_to_slice`]/[`VarInt::decode_from_slice`], allocation-free over `&[u8]`
+//! - [`VarInt::encode_async`]/[`VarInt::decode_async`], over `tokio::io`, behind the `tokio`
+//!   cargo feature
+//! - [`write_length_delimited`]/[`read_length_delimited`], for length-prefixed byte blobs
 
+use std::error::Error as StdError;
 use std::fmt;
 use std::io::{Read, Write};
 
 use anyhow::{bail, Error, Result};
+use bytes::{Buf, BufMut};
 
 /// An integer less than 2^62
 ///
@@ -66,32 +77,346 @@ impl VarInt {
 
     /// Decodes a VarInt from a reader.
     pub fn decode<R: Read>(mut reader: R) -> Result<Self> {
-        let mut buf = [0u8; 8];
-        reader.read_exact(&mut buf[0..1])?;
-        let num_bytes = Self::encoded_size(buf[0]);
-        buf[0] &= 0b0011_1111;
-        let val = match num_bytes {
-            1 => VarInt::from(buf[0]),
-            2 => {
-                reader.read_exact(&mut buf[1..2])?;
-                let val = u16::from_be_bytes(buf[..2].try_into()?);
-                VarInt::from(val)
-            }
+        let mut first = [0u8; 1];
+        reader.read_exact(&mut first)?;
+        let num_bytes = Self::encoded_size(first[0]);
+        let tag = first[0] & 0b0011_1111;
+        let mut tail = [0u8; 7];
+        reader.read_exact(&mut tail[..num_bytes - 1])?;
+        Self::assemble(tag, &tail[..num_bytes - 1])
+    }
+
+    /// Assembles a VarInt from its tag bits (the low 6 bits of the first byte) and the
+    /// remaining `num_bytes - 1` big-endian bytes. Shared by every decode path.
+    fn assemble(tag: u8, tail: &[u8]) -> Result<Self> {
+        let val = match tail.len() + 1 {
+            1 => VarInt::from(tag),
+            2 => VarInt::from(u16::from_be_bytes([tag, tail[0]])),
             4 => {
-                reader.read_exact(&mut buf[1..4])?;
-                let val = u32::from_be_bytes(buf[..4].try_into()?);
-                VarInt::from(val)
+                let mut bytes = [0u8; 4];
+                bytes[0] = tag;
+                bytes[1..].copy_from_slice(tail);
+                VarInt::from(u32::from_be_bytes(bytes))
             }
             8 => {
-                reader.read_exact(&mut buf[1..8])?;
-                VarInt::try_from(u64::from_be_bytes(buf))?
+                let mut bytes = [0u8; 8];
+                bytes[0] = tag;
+                bytes[1..].copy_from_slice(tail);
+                VarInt::try_from(u64::from_be_bytes(bytes))?
             }
             _ => bail!("Invalid VarInt tag"),
         };
         Ok(val)
     }
+
+    /// Encodes the VarInt into a `BufMut`, without going through `Write`.
+    ///
+    /// Panics if `buf` has less than [`VarInt::size`] bytes of remaining capacity.
+    pub fn encode_buf<B: BufMut>(&self, buf: &mut B) {
+        let x = self.0;
+        if x < 2u64.pow(6) {
+            buf.put_u8(x as u8);
+        } else if x < 2u64.pow(14) {
+            buf.put_u16(0b01 << 14 | x as u16);
+        } else if x < 2u64.pow(30) {
+            buf.put_u32(0b10 << 30 | x as u32);
+        } else if x < 2u64.pow(62) {
+            buf.put_u64(0b11 << 62 | x);
+        } else {
+            unreachable!("malformed VarInt")
+        }
+    }
+
+    /// Decodes a VarInt from a `Buf`, without going through `Read`.
+    ///
+    /// Returns [`UnexpectedEnd`] if `buf` does not contain a complete value.
+    pub fn decode_buf<B: Buf>(buf: &mut B) -> Result<Self> {
+        if !buf.has_remaining() {
+            return Err(UnexpectedEnd.into());
+        }
+        let first = buf.get_u8();
+        let num_bytes = Self::encoded_size(first);
+        if buf.remaining() < num_bytes - 1 {
+            return Err(UnexpectedEnd.into());
+        }
+        let tag = first & 0b0011_1111;
+        let mut tail = [0u8; 7];
+        buf.copy_to_slice(&mut tail[..num_bytes - 1]);
+        Self::assemble(tag, &tail[..num_bytes - 1])
+    }
+
+    /// Encodes the VarInt into `buf`, returning the filled prefix.
+    ///
+    /// Panics if `buf` is shorter than [`VarInt::size`].
+    pub fn encode_to_slice(self, buf: &mut [u8]) -> &[u8] {
+        let size = self.size();
+        let x = self.0;
+        match size {
+            1 => buf[0] = x as u8,
+            2 => buf[..2].copy_from_slice(&(0b01 << 14 | x as u16).to_be_bytes()),
+            4 => buf[..4].copy_from_slice(&(0b10 << 30 | x as u32).to_be_bytes()),
+            8 => buf[..8].copy_from_slice(&(0b11 << 62 | x).to_be_bytes()),
+            _ => unreachable!("malformed VarInt"),
+        }
+        &buf[..size]
+    }
+
+    /// Decodes a VarInt from the start of `buf`, returning the value and the unconsumed tail.
+    pub fn decode_from_slice(buf: &[u8]) -> Result<(Self, &[u8])> {
+        let Some(&first) = buf.first() else {
+            return Err(UnexpectedEnd.into());
+        };
+        let num_bytes = Self::encoded_size(first);
+        if buf.len() < num_bytes {
+            return Err(UnexpectedEnd.into());
+        }
+        let tag = first & 0b0011_1111;
+        let val = Self::assemble(tag, &buf[1..num_bytes])?;
+        Ok((val, &buf[num_bytes..]))
+    }
 }
 
+#[cfg(test)]
+mod buf_codec_tests {
+    use super::{UnexpectedEnd, VarInt};
+    use bytes::BytesMut;
+
+    fn size_classes() -> [u64; 4] {
+        [0, 2u64.pow(6), 2u64.pow(14), 2u64.pow(30)]
+    }
+
+    #[test]
+    fn encode_buf_matches_encode() {
+        for n in size_classes() {
+            let v = VarInt::try_from(n).unwrap();
+            let mut via_write = Vec::new();
+            v.encode(&mut via_write).unwrap();
+            let mut via_buf = BytesMut::new();
+            v.encode_buf(&mut via_buf);
+            assert_eq!(via_write, via_buf.as_ref());
+        }
+    }
+
+    #[test]
+    fn decode_buf_matches_decode() {
+        for n in size_classes() {
+            let v = VarInt::try_from(n).unwrap();
+            let mut encoded = BytesMut::new();
+            v.encode_buf(&mut encoded);
+            let decoded = VarInt::decode_buf(&mut encoded).unwrap();
+            assert_eq!(decoded, v);
+        }
+    }
+
+    #[test]
+    fn decode_buf_returns_unexpected_end_on_truncated_input() {
+        let v = VarInt::try_from(1u64 << 30).unwrap(); // an 8-byte encoding
+        let mut encoded = BytesMut::new();
+        v.encode_buf(&mut encoded);
+        let mut truncated = encoded.split_to(encoded.len() - 1);
+        let err = VarInt::decode_buf(&mut truncated).unwrap_err();
+        assert!(err.downcast_ref::<UnexpectedEnd>().is_some());
+    }
+
+    #[test]
+    fn decode_buf_returns_unexpected_end_on_empty_input() {
+        let mut empty = BytesMut::new();
+        let err = VarInt::decode_buf(&mut empty).unwrap_err();
+        assert!(err.downcast_ref::<UnexpectedEnd>().is_some());
+    }
+}
+
+#[cfg(test)]
+mod slice_codec_tests {
+    use super::{UnexpectedEnd, VarInt};
+
+    fn size_classes() -> [u64; 4] {
+        [0, 2u64.pow(6), 2u64.pow(14), 2u64.pow(30)]
+    }
+
+    #[test]
+    fn encode_to_slice_matches_encode() {
+        for n in size_classes() {
+            let v = VarInt::try_from(n).unwrap();
+            let mut via_write = Vec::new();
+            v.encode(&mut via_write).unwrap();
+            let mut buf = [0u8; 8];
+            let via_slice = v.encode_to_slice(&mut buf);
+            assert_eq!(via_write, via_slice);
+        }
+    }
+
+    #[test]
+    fn decode_from_slice_matches_decode_and_returns_unconsumed_tail() {
+        for n in size_classes() {
+            let v = VarInt::try_from(n).unwrap();
+            let mut buf = [0u8; 9];
+            let written = v.encode_to_slice(&mut buf[..8]).len();
+            buf[written] = 0xff; // trailing byte that must be returned, not consumed
+            let (decoded, tail) = VarInt::decode_from_slice(&buf[..written + 1]).unwrap();
+            assert_eq!(decoded, v);
+            assert_eq!(tail, &[0xff]);
+        }
+    }
+
+    #[test]
+    fn decode_from_slice_returns_unexpected_end_on_truncated_input() {
+        let v = VarInt::try_from(1u64 << 30).unwrap(); // an 8-byte encoding
+        let mut buf = [0u8; 8];
+        let written = v.encode_to_slice(&mut buf).len();
+        let err = VarInt::decode_from_slice(&buf[..written - 1]).unwrap_err();
+        assert!(err.downcast_ref::<UnexpectedEnd>().is_some());
+    }
+
+    #[test]
+    fn decode_from_slice_returns_unexpected_end_on_empty_input() {
+        let err = VarInt::decode_from_slice(&[]).unwrap_err();
+        assert!(err.downcast_ref::<UnexpectedEnd>().is_some());
+    }
+}
+
+/// Async encode/decode support over `tokio::io`, for streaming protocols that read varints off
+/// a socket rather than a pre-filled buffer. Gated behind the `tokio` feature.
+#[cfg(feature = "tokio")]
+mod tokio_io {
+    use super::{Result, VarInt};
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+    impl VarInt {
+        /// Encodes the VarInt to an async writer.
+        pub async fn encode_async<W: AsyncWrite + Unpin>(&self, mut writer: W) -> Result<()> {
+            let mut buf = [0u8; 8];
+            let slice = self.encode_to_slice(&mut buf);
+            writer.write_all(slice).await?;
+            Ok(())
+        }
+
+        /// Decodes a VarInt from an async reader.
+        pub async fn decode_async<R: AsyncRead + Unpin>(mut reader: R) -> Result<Self> {
+            let first = reader.read_u8().await?;
+            let num_bytes = Self::encoded_size(first);
+            let tag = first & 0b0011_1111;
+            let mut tail = [0u8; 7];
+            reader.read_exact(&mut tail[..num_bytes - 1]).await?;
+            Self::assemble(tag, &tail[..num_bytes - 1])
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::VarInt;
+
+        fn size_classes() -> [u64; 4] {
+            [0, 2u64.pow(6), 2u64.pow(14), 2u64.pow(30)]
+        }
+
+        #[tokio::test]
+        async fn encode_async_matches_encode() {
+            for n in size_classes() {
+                let v = VarInt::try_from(n).unwrap();
+                let mut via_write = Vec::new();
+                v.encode(&mut via_write).unwrap();
+                let mut via_async = Vec::new();
+                v.encode_async(&mut via_async).await.unwrap();
+                assert_eq!(via_write, via_async);
+            }
+        }
+
+        #[tokio::test]
+        async fn decode_async_matches_decode() {
+            for n in size_classes() {
+                let v = VarInt::try_from(n).unwrap();
+                let mut encoded = Vec::new();
+                v.encode(&mut encoded).unwrap();
+                let decoded = VarInt::decode_async(&encoded[..]).await.unwrap();
+                assert_eq!(decoded, v);
+            }
+        }
+
+        #[tokio::test]
+        async fn decode_async_errors_on_truncated_input() {
+            let v = VarInt::try_from(1u64 << 30).unwrap(); // an 8-byte encoding
+            let mut encoded = Vec::new();
+            v.encode(&mut encoded).unwrap();
+            encoded.truncate(encoded.len() - 1);
+            assert!(VarInt::decode_async(&encoded[..]).await.is_err());
+        }
+    }
+}
+
+/// Writes `data` as a length-delimited blob: its length encoded as a [`VarInt`], followed by the
+/// bytes themselves.
+pub fn write_length_delimited<W: Write>(mut writer: W, data: &[u8]) -> Result<()> {
+    let len = VarInt::try_from(data.len())?;
+    len.encode(&mut writer)?;
+    writer.write_all(data)?;
+    Ok(())
+}
+
+/// Reads a length-delimited blob written by [`write_length_delimited`].
+///
+/// The decoded length is checked against `max_len` before allocating, so a malformed varint
+/// claiming a huge length cannot be used to force a large allocation.
+pub fn read_length_delimited<R: Read>(mut reader: R, max_len: usize) -> Result<Vec<u8>> {
+    let len = VarInt::decode(&mut reader)?;
+    let len = usize::try_from(u64::from(len))
+        .map_err(|_| Error::msg("length-delimited blob length does not fit in usize"))?;
+    if len > max_len {
+        bail!("length-delimited blob of {len} bytes exceeds max_len of {max_len}");
+    }
+    let mut data = vec![0u8; len];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod length_delimited_tests {
+    use super::{read_length_delimited, write_length_delimited, VarInt};
+
+    #[test]
+    fn rejects_length_over_max_len_before_reading_data() {
+        let mut buf = Vec::new();
+        // Claims a 100-byte blob but no data bytes follow. If the implementation read the data
+        // before checking `max_len`, this would surface as an I/O error instead.
+        VarInt::try_from(100u64).unwrap().encode(&mut buf).unwrap();
+        let err = read_length_delimited(&buf[..], 10).unwrap_err();
+        assert!(err.to_string().contains("exceeds max_len"));
+    }
+
+    #[test]
+    fn accepts_length_exactly_at_max_len() {
+        let mut buf = Vec::new();
+        let data = vec![7u8; 16];
+        write_length_delimited(&mut buf, &data).unwrap();
+        let got = read_length_delimited(&buf[..], 16).unwrap();
+        assert_eq!(got, data);
+    }
+
+    // `VarInt` tops out at 2^62 - 1, which always fits a 64-bit `usize`; the overflow this
+    // guards against can only happen where `usize` is narrower than 64 bits.
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn rejects_length_that_does_not_fit_usize() {
+        let mut buf = Vec::new();
+        VarInt::MAX.encode(&mut buf).unwrap();
+        let err = read_length_delimited(&buf[..], usize::MAX).unwrap_err();
+        assert!(err.to_string().contains("does not fit in usize"));
+    }
+}
+
+/// Error returned by [`VarInt::decode_buf`] or [`VarInt::decode_from_slice`] when the buffer ends
+/// before a full value is read.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct UnexpectedEnd;
+
+impl fmt::Display for UnexpectedEnd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unexpected end of buffer while decoding VarInt")
+    }
+}
+
+impl StdError for UnexpectedEnd {}
+
 impl From<VarInt> for u64 {
     fn from(x: VarInt) -> u64 {
         x.0
@@ -142,3 +467,99 @@ impl fmt::Display for VarInt {
         self.0.fmt(f)
     }
 }
+
+/// A signed integer, encoded via zig-zag mapping onto a [`VarInt`].
+///
+/// This reuses `VarInt`'s encoding, so small-magnitude negative values stay
+/// just as short as small-magnitude positive ones.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SignedVarInt(i64);
+
+impl SignedVarInt {
+    /// Compute the number of bytes needed to encode this value
+    pub fn size(self) -> usize {
+        self.to_varint().size()
+    }
+
+    /// Encodes the SignedVarInt to a writer.
+    pub fn encode<W: Write>(&self, writer: W) -> Result<()> {
+        self.to_varint().encode(writer)
+    }
+
+    /// Decodes a SignedVarInt from a reader.
+    pub fn decode<R: Read>(reader: R) -> Result<Self> {
+        let varint = VarInt::decode(reader)?;
+        Ok(Self::from_varint(varint))
+    }
+
+    fn to_varint(self) -> VarInt {
+        let n = self.0;
+        let u = ((n << 1) ^ (n >> 63)) as u64;
+        VarInt(u)
+    }
+
+    fn from_varint(varint: VarInt) -> Self {
+        let u = varint.0;
+        let n = ((u >> 1) as i64) ^ -((u & 1) as i64);
+        SignedVarInt(n)
+    }
+}
+
+impl From<SignedVarInt> for i64 {
+    fn from(x: SignedVarInt) -> i64 {
+        x.0
+    }
+}
+
+impl TryFrom<i64> for SignedVarInt {
+    type Error = Error;
+
+    fn try_from(x: i64) -> Result<Self> {
+        let u = ((x << 1) ^ (x >> 63)) as u64;
+        if u < 2u64.pow(62) {
+            Ok(SignedVarInt(x))
+        } else {
+            Err(Error::msg("SignedVarInt bounds exceeded"))
+        }
+    }
+}
+
+impl fmt::Display for SignedVarInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod signed_varint_tests {
+    use super::SignedVarInt;
+
+    #[test]
+    fn round_trips_near_zero_and_size_class_boundaries() {
+        // 31 and -32 zig-zag to 62 and 63 (1 byte); 32 and -33 zig-zag to 64 and 65 (2 bytes).
+        for n in [0i64, -1, 31, -32, 32, -33, 63, -64, 64, -65] {
+            let encoded = SignedVarInt::try_from(n).unwrap();
+            let mut buf = Vec::new();
+            encoded.encode(&mut buf).unwrap();
+            let decoded = SignedVarInt::decode(&buf[..]).unwrap();
+            assert_eq!(i64::from(decoded), n);
+        }
+    }
+
+    #[test]
+    fn round_trips_at_the_magnitude_cutoff() {
+        for n in [(1i64 << 61) - 1, -(1i64 << 61)] {
+            let encoded = SignedVarInt::try_from(n).unwrap();
+            let mut buf = Vec::new();
+            encoded.encode(&mut buf).unwrap();
+            let decoded = SignedVarInt::decode(&buf[..]).unwrap();
+            assert_eq!(i64::from(decoded), n);
+        }
+    }
+
+    #[test]
+    fn rejects_magnitudes_past_the_cutoff() {
+        assert!(SignedVarInt::try_from(1i64 << 61).is_err());
+        assert!(SignedVarInt::try_from(-(1i64 << 61) - 1).is_err());
+    }
+}